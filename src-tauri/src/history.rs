@@ -0,0 +1,155 @@
+//! A bounded in-memory ring buffer of encoded frames, with frames older
+//! than the ring spilled to a per-run temp directory on disk keyed by
+//! sequence number. Memory stays bounded while a longer history remains
+//! retrievable for scrubbing, and the total history (memory + disk) is
+//! itself capped so long-running sessions don't accumulate spill files
+//! forever.
+//!
+//! Callers must supply sequence numbers that never repeat across the
+//! lifetime of a `FrameHistory` — e.g. an `AtomicU64` counter owned
+//! alongside it, not a value that can reset (like a per-invocation loop
+//! counter) when capture restarts. Frames are kept in the order they are
+//! pushed, which need not be sequence-number order.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// How many encoded frames are kept in memory before older ones spill to
+/// disk.
+const IN_MEMORY_CAPACITY: usize = 30;
+
+/// Total number of frames (in memory + spilled to disk) retained before the
+/// oldest is evicted and its spill file deleted. Bounds disk usage and
+/// lookup cost for long-running sessions.
+const TOTAL_HISTORY_CAPACITY: usize = 300;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct FrameSummary {
+    pub seq: u64,
+    pub timestamp_ms: u64,
+}
+
+struct StoredFrame {
+    seq: u64,
+    timestamp_ms: u64,
+    mime: &'static str,
+    bytes: Option<Vec<u8>>, // None once spilled to disk
+}
+
+pub struct FrameHistory {
+    spill_dir: PathBuf,
+    frames: VecDeque<StoredFrame>,
+    in_memory_count: usize,
+}
+
+impl FrameHistory {
+    pub fn new() -> Self {
+        let spill_dir = std::env::temp_dir().join(format!("rustaid-frames-{}", std::process::id()));
+        let _ = fs::create_dir_all(&spill_dir);
+
+        Self {
+            spill_dir,
+            frames: VecDeque::new(),
+            in_memory_count: 0,
+        }
+    }
+
+    fn spill_path(&self, seq: u64) -> PathBuf {
+        self.spill_dir.join(format!("{seq}.bin"))
+    }
+
+    /// Stores a newly encoded frame, spilling the oldest still-in-memory
+    /// frame to disk if this pushes us over `IN_MEMORY_CAPACITY`.
+    pub fn push(&mut self, seq: u64, mime: &'static str, bytes: Vec<u8>) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        self.frames.push_back(StoredFrame {
+            seq,
+            timestamp_ms,
+            mime,
+            bytes: Some(bytes),
+        });
+        self.in_memory_count += 1;
+
+        while self.in_memory_count > IN_MEMORY_CAPACITY {
+            let Some(oldest) = self
+                .frames
+                .iter_mut()
+                .find(|frame| frame.bytes.is_some())
+            else {
+                break;
+            };
+
+            if let Some(bytes) = oldest.bytes.take() {
+                let _ = fs::write(self.spill_path(oldest.seq), bytes);
+                self.in_memory_count -= 1;
+            }
+        }
+
+        // Evict the oldest frame entirely (deleting its spill file, if any)
+        // once the total history, not just the in-memory portion, grows
+        // past its cap.
+        while self.frames.len() > TOTAL_HISTORY_CAPACITY {
+            let Some(evicted) = self.frames.pop_front() else {
+                break;
+            };
+
+            match evicted.bytes {
+                Some(_) => self.in_memory_count -= 1,
+                None => {
+                    let _ = fs::remove_file(self.spill_path(evicted.seq));
+                }
+            }
+        }
+    }
+
+    /// Returns the bytes and mime type of the most recently pushed frame.
+    pub fn latest(&self) -> Option<(Vec<u8>, &'static str)> {
+        let newest = self.frames.back()?;
+        self.read(newest)
+    }
+
+    /// Returns the bytes and mime type for a specific sequence number,
+    /// reading from the in-memory ring or from disk as needed.
+    pub fn get(&self, seq: u64) -> Option<(Vec<u8>, &'static str)> {
+        let frame = self.frames.iter().find(|frame| frame.seq == seq)?;
+        self.read(frame)
+    }
+
+    fn read(&self, frame: &StoredFrame) -> Option<(Vec<u8>, &'static str)> {
+        match &frame.bytes {
+            Some(bytes) => Some((bytes.clone(), frame.mime)),
+            None => fs::read(self.spill_path(frame.seq))
+                .ok()
+                .map(|bytes| (bytes, frame.mime)),
+        }
+    }
+
+    pub fn contains(&self, seq: u64) -> bool {
+        self.frames.iter().any(|frame| frame.seq == seq)
+    }
+
+    /// Lists all available sequence numbers and timestamps, oldest first.
+    pub fn list(&self) -> Vec<FrameSummary> {
+        self.frames
+            .iter()
+            .map(|frame| FrameSummary {
+                seq: frame.seq,
+                timestamp_ms: frame.timestamp_ms,
+            })
+            .collect()
+    }
+}
+
+impl Drop for FrameHistory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.spill_dir);
+    }
+}
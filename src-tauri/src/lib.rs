@@ -1,22 +1,120 @@
-use base64::{alphabet, engine, Engine};
-use image::{DynamicImage, ImageFormat, RgbImage};
+mod capture;
+mod history;
+mod quantize;
+
+use capture::{CaptureConfig, CropArea, TargetSummary};
+use history::{FrameHistory, FrameSummary};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageEncoder, ImageFormat, RgbImage};
+use quantize::NeuQuant;
 use scap::{
     capturer::{Capturer, Options},
     frame::Frame,
-    get_all_targets, Target,
+    Target,
 };
 use std::io::Cursor;
 use std::{
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     thread::sleep,
 };
-use tauri::State;
-use tokio::{task, time::Duration};
+use tauri::{
+    http::{Response, StatusCode},
+    Emitter, Manager, State,
+};
+use tokio::sync::broadcast;
+use tokio::{sync::Semaphore, task, time::Duration};
+
+/// Default number of frames that may be mid-encode at once, until changed
+/// at runtime via `set_encode_permits`. Bounds memory growth if encoding
+/// falls behind the capture loop.
+const DEFAULT_ENCODE_PERMITS: usize = 4;
+
+/// Capacity of the frame-ready broadcast channel. Lagging subscribers drop
+/// old notifications rather than blocking the capture loop.
+const FRAME_CHANNEL_CAPACITY: usize = 16;
+
+/// Roughly every Nth pixel is used when training the NeuQuant palette,
+/// trading accuracy for training speed on full-resolution frames.
+const QUANTIZE_SAMPLE_FACTOR: u32 = 10;
+
+/// Codec used to encode captured frames before they are served to the
+/// frontend. JPEG and WebP trade a little quality for a much smaller
+/// payload than PNG, which matters when polling at any real framerate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::WebP
+    }
+}
+
+impl OutputFormat {
+    fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+        }
+    }
+}
 
-#[derive(Default)]
 struct AppState {
     pub window: Mutex<String>,
-    pub last_frame: Mutex<Option<String>>, // Store Base64 frame
+    // Ring buffer of recently encoded frames; bounds memory while spilling
+    // older frames to disk so they remain retrievable for scrubbing.
+    pub history: Mutex<FrameHistory>,
+    // Monotonic source of frame sequence numbers. Owned by AppState (not a
+    // per-invocation local) so it keeps counting up across capture restarts
+    // instead of colliding with sequence numbers already in `history`.
+    pub next_seq: AtomicU64,
+    pub output_format: Mutex<OutputFormat>,
+    pub quality: Mutex<u8>, // 0-100, ignored for Png
+    // Bounds the number of in-flight encode tasks; acquired before spawning
+    // and released when the task completes, so a slow encoder causes frames
+    // to be dropped instead of piling up unbounded work. Held behind a
+    // `Mutex` (rather than a fixed `Semaphore`) so `set_encode_permits` can
+    // swap in a differently-sized one at runtime; permits already acquired
+    // against the old semaphore remain valid until their task finishes.
+    pub encode_permits: Mutex<Arc<Semaphore>>,
+    // When set, Png output is palette-quantized via NeuQuant before
+    // encoding, trading a little color fidelity for a much smaller frame.
+    pub quantize: Mutex<bool>,
+    pub color_count: Mutex<u16>, // 2-256, only used when quantize is set
+    // Notifies subscribers of each newly captured frame's sequence number.
+    // Broadcast channels never block the sender on a slow subscriber; a
+    // lagging one just misses old notifications instead.
+    pub frame_tx: broadcast::Sender<u64>,
+    // Which window/display to capture, at what resolution/fps/crop. The
+    // capture loop polls this and restarts against the new selection when
+    // it changes.
+    pub capture_config: Mutex<CaptureConfig>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        let (frame_tx, _) = broadcast::channel(FRAME_CHANNEL_CAPACITY);
+
+        Self {
+            window: Mutex::new(String::new()),
+            history: Mutex::new(FrameHistory::new()),
+            next_seq: AtomicU64::new(0),
+            output_format: Mutex::new(OutputFormat::default()),
+            quality: Mutex::new(75),
+            encode_permits: Mutex::new(Arc::new(Semaphore::new(DEFAULT_ENCODE_PERMITS))),
+            quantize: Mutex::new(false),
+            color_count: Mutex::new(256),
+            frame_tx,
+            capture_config: Mutex::new(CaptureConfig::default()),
+        }
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -28,10 +126,64 @@ pub async fn run() {
         startup(state_clone).await;
     });
 
+    let protocol_state = state.clone();
+
     tauri::Builder::default()
         .manage(state)
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![get_window, get_frame])
+        .register_uri_scheme_protocol("frame", move |_app, request| {
+            let history = protocol_state.history.lock().unwrap();
+            let host = request.uri().host().unwrap_or("latest");
+
+            let frame = if host == "latest" {
+                history.latest()
+            } else {
+                host.parse::<u64>().ok().and_then(|seq| history.get(seq))
+            };
+
+            match frame {
+                Some((bytes, mime)) => Response::builder()
+                    .header("Content-Type", mime)
+                    .header("Cache-Control", "no-store")
+                    .status(StatusCode::OK)
+                    .body(bytes)
+                    .unwrap(),
+                None => Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Vec::new())
+                    .unwrap(),
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            get_window,
+            get_frame,
+            get_frame_at,
+            list_frames,
+            set_output_format,
+            set_quantization,
+            set_encode_permits,
+            get_targets,
+            set_target
+        ])
+        .setup(|app| {
+            let state = app.state::<Arc<AppState>>();
+            let mut frame_rx = state.frame_tx.subscribe();
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    match frame_rx.recv().await {
+                        Ok(seq) => {
+                            let _ = app_handle.emit("frame-ready", seq);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            Ok(())
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -51,61 +203,101 @@ async fn startup(state: Arc<AppState>) {
     }
 
     loop {
-        if let Some(rust) = get_rust_target() {
-            rust_capture(rust, state.clone());
+        let config = state.capture_config.lock().unwrap().clone();
+        if let Some(target) = capture::resolve_target(&config) {
+            rust_capture(target, config, state.clone());
         }
         sleep(Duration::from_secs(1));
     }
 }
 
-fn rust_capture(rust: Target, state: Arc<AppState>) {
-    // Used to encode immages to strings.
-    let encoder = engine::GeneralPurpose::new(&alphabet::STANDARD, engine::general_purpose::NO_PAD);
-
+fn rust_capture(target: Target, config: CaptureConfig, state: Arc<AppState>) {
     let mut capturer = Capturer::build(Options {
-        fps: 1,
-        target: Some(rust),
+        fps: config.fps,
+        target: Some(target),
         show_cursor: true,
         show_highlight: true,
         excluded_targets: None,
         output_type: scap::frame::FrameType::RGB,
-        output_resolution: scap::capturer::Resolution::_1080p,
-        crop_area: None,
+        output_resolution: config.resolution,
+        crop_area: config.crop_area.map(CropArea::into_area),
     })
     .unwrap();
 
-    let mut frame_count: u32 = 0;
+    // Local to this invocation and reset on every restart; used only for
+    // log messages below. Frame *sequence numbers* come from
+    // `state.next_seq` instead, which is owned by AppState and keeps
+    // counting across restarts so `history` never sees a repeated seq.
+    let mut loop_iteration: u32 = 0;
 
     // Reusable buffer to prevent continuous reallocation
     let buffer: Vec<u8> = Vec::with_capacity(1920 * 1080 * 3);
 
     loop {
-        frame_count += 1;
+        // Pick up target/resolution/fps changes made at runtime by
+        // restarting capture against the freshly resolved configuration.
+        // This re-enters `rust_capture`, so anything that must survive a
+        // restart (like frame sequence numbers) has to live on `state`,
+        // not as a local here.
+        if state.capture_config.lock().unwrap().version != config.version {
+            println!("🔁 Capture configuration changed, restarting capture");
+            capturer.stop_capture();
+            return;
+        }
+
+        loop_iteration += 1;
 
         capturer.start_capture();
 
         match capturer.get_next_frame() {
             Ok(frame) => {
-                println!("{}: ✅ Frame captured successfully!", frame_count);
+                println!("{}: ✅ Frame captured successfully!", loop_iteration);
 
                 if let Frame::BGRA(frame_data) = frame {
-                    let encoder = encoder.clone();
-                    let mut buffer = buffer.clone();
-                    let state = state.clone();
-
-                    task::spawn(async move {
-                        let base64_image = frame_to_base64(
-                            &encoder,
-                            &mut buffer, // Reuse buffer
-                            &frame_data.data,
-                            frame_data.width as u32,
-                            frame_data.height as u32,
-                        );
-
-                        // Store frame in state
-                        let mut last_frame = state.last_frame.lock().unwrap();
-                        *last_frame = Some(base64_image);
-                    });
+                    let encode_permits = state.encode_permits.lock().unwrap().clone();
+                    match encode_permits.try_acquire_owned() {
+                        Ok(permit) => {
+                            let mut buffer = buffer.clone();
+                            let state = state.clone();
+
+                            let format = *state.output_format.lock().unwrap();
+                            let quality = *state.quality.lock().unwrap();
+                            let quantize = *state.quantize.lock().unwrap();
+                            let color_count = *state.color_count.lock().unwrap();
+                            // A monotonic counter owned by AppState, not a
+                            // local, so sequence numbers keep increasing
+                            // across capture restarts instead of colliding
+                            // with frames already in `history`.
+                            let seq = state.next_seq.fetch_add(1, Ordering::Relaxed);
+
+                            task::spawn(async move {
+                                let _permit = permit; // held until encode finishes
+
+                                let bytes = encode_frame(
+                                    &mut buffer, // Reuse buffer
+                                    &frame_data.data,
+                                    frame_data.width as u32,
+                                    frame_data.height as u32,
+                                    format,
+                                    quality,
+                                    quantize,
+                                    color_count,
+                                );
+
+                                // Store frame in history and notify subscribers
+                                let mut history = state.history.lock().unwrap();
+                                history.push(seq, format.mime_type(), bytes);
+                                drop(history);
+                                let _ = state.frame_tx.send(seq);
+                            });
+                        }
+                        Err(_) => {
+                            println!(
+                                "⚠️ Encode backlog full, dropping frame {}",
+                                loop_iteration
+                            );
+                        }
+                    }
                 }
             }
             Err(e) => {
@@ -121,14 +313,20 @@ fn rust_capture(rust: Target, state: Arc<AppState>) {
     }
 }
 
-/// Converts a frame to a Base64 encoded PNG
-fn frame_to_base64<T: Engine>(
-    encoder: &T,
+/// Encodes a frame to raw image bytes using the chosen codec.
+/// `quality` is in the 0-100 range and is ignored for `OutputFormat::Png`.
+/// `quantize`/`color_count` only apply to `OutputFormat::Png`, producing a
+/// palette-indexed PNG instead of a full RGB one.
+fn encode_frame(
     buffer: &mut Vec<u8>,
     bgra_data: &[u8],
     width: u32,
     height: u32,
-) -> String {
+    format: OutputFormat,
+    quality: u8,
+    quantize: bool,
+    color_count: u16,
+) -> Vec<u8> {
     buffer.clear(); // Reuse memory, don't reallocate
 
     // Convert BGRA to RGB directly without using `flat_map`
@@ -138,18 +336,59 @@ fn frame_to_base64<T: Engine>(
         buffer.push(chunk[2]); // B
     }
 
-    let dynamic_image =
-        DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, buffer.clone()).unwrap());
+    match format {
+        OutputFormat::Png if quantize => encode_indexed_png(buffer, width, height, color_count),
+        OutputFormat::Png => {
+            let rgb_image = RgbImage::from_raw(width, height, buffer.clone()).unwrap();
+            let mut img_bytes = Cursor::new(Vec::new());
+            DynamicImage::ImageRgb8(rgb_image)
+                .write_to(&mut img_bytes, ImageFormat::Png)
+                .expect("Failed to write image to bytes");
+            img_bytes.into_inner()
+        }
+        OutputFormat::Jpeg => {
+            let mut img_bytes = Cursor::new(Vec::new());
+            JpegEncoder::new_with_quality(&mut img_bytes, quality)
+                .write_image(buffer, width, height, image::ExtendedColorType::Rgb8)
+                .expect("Failed to write image to bytes");
+            img_bytes.into_inner()
+        }
+        OutputFormat::WebP => webp::Encoder::from_rgb(buffer, width, height)
+            .encode(quality as f32)
+            .to_vec(),
+    }
+}
 
-    // Convert the image to a byte vector in PNG format using ImageFormat
-    let mut img_bytes = Cursor::new(Vec::new());
-    dynamic_image
-        .write_to(&mut img_bytes, ImageFormat::Png)
-        .expect("Failed to write image to bytes");
+/// Quantizes `rgb_pixels` to a palette of `color_count` colors via NeuQuant
+/// and encodes the result as an indexed PNG. Falls back to a plain RGB PNG
+/// if the frame is too small to train a palette from.
+fn encode_indexed_png(rgb_pixels: &[u8], width: u32, height: u32, color_count: u16) -> Vec<u8> {
+    let mut bytes = Vec::new();
 
-    // Encode the byte vector (through Cursor) to a base64 string
-    encoder.encode(img_bytes.get_ref())
-    //"goon".to_string()
+    match NeuQuant::train(rgb_pixels, color_count as usize, QUANTIZE_SAMPLE_FACTOR) {
+        Some(quant) => {
+            let indices = quant.quantize_image(rgb_pixels);
+            let palette: Vec<u8> = quant.palette().into_iter().flatten().collect();
+
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_palette(palette);
+            let mut writer = encoder.write_header().expect("Failed to write PNG header");
+            writer
+                .write_image_data(&indices)
+                .expect("Failed to write indexed PNG data");
+        }
+        None => {
+            let rgb_image = RgbImage::from_raw(width, height, rgb_pixels.to_vec()).unwrap();
+            let mut cursor = Cursor::new(&mut bytes);
+            DynamicImage::ImageRgb8(rgb_image)
+                .write_to(&mut cursor, ImageFormat::Png)
+                .expect("Failed to write image to bytes");
+        }
+    }
+
+    bytes
 }
 
 #[tauri::command]
@@ -158,17 +397,119 @@ fn get_window(state: State<Arc<AppState>>) -> Result<String, String> {
     Ok(window.clone())
 }
 
+/// Returns the sequence number of the most recently stored frame (the one
+/// `frame://latest` serves; with concurrent encodes this need not be the
+/// highest sequence number captured so far). The
+/// frontend uses this to know when to re-fetch `frame://latest`, rather
+/// than receiving the (potentially large) image bytes directly.
+#[tauri::command]
+fn get_frame(state: State<Arc<AppState>>) -> Result<u64, String> {
+    let history = state.history.lock().unwrap();
+    history
+        .list()
+        .last()
+        .map(|frame| frame.seq)
+        .ok_or_else(|| "No frame captured yet".into())
+}
+
+/// Confirms a previously captured frame is still available (in memory or
+/// spilled to disk), so the frontend can fetch it from `frame://<seq>`.
+#[tauri::command]
+fn get_frame_at(state: State<Arc<AppState>>, seq: u64) -> Result<u64, String> {
+    let history = state.history.lock().unwrap();
+    if history.contains(seq) {
+        Ok(seq)
+    } else {
+        Err(format!("frame {seq} not available"))
+    }
+}
+
+/// Lists the sequence numbers and timestamps of all frames currently
+/// retrievable, oldest first, enabling the frontend to scrub backwards
+/// through recent capture instead of only seeing "live".
+#[tauri::command]
+fn list_frames(state: State<Arc<AppState>>) -> Result<Vec<FrameSummary>, String> {
+    let history = state.history.lock().unwrap();
+    Ok(history.list())
+}
+
+/// Sets the codec and quality used for subsequently captured frames.
+/// `format` is one of "png", "jpeg", "webp" (case-insensitive); `quality`
+/// is clamped to 0-100.
+#[tauri::command]
+fn set_output_format(
+    state: State<Arc<AppState>>,
+    format: String,
+    quality: u8,
+) -> Result<(), String> {
+    let parsed = match format.to_lowercase().as_str() {
+        "png" => OutputFormat::Png,
+        "jpeg" | "jpg" => OutputFormat::Jpeg,
+        "webp" => OutputFormat::WebP,
+        other => return Err(format!("unknown output format: {other}")),
+    };
+
+    *state.output_format.lock().unwrap() = parsed;
+    *state.quality.lock().unwrap() = quality.min(100);
+    Ok(())
+}
+
+/// Enables or disables NeuQuant palette quantization for Png output.
+/// `color_count` is clamped to 2-256 and is ignored when `quantize` is
+/// false.
 #[tauri::command]
-fn get_frame(state: State<Arc<AppState>>) -> Result<String, String> {
-    let frame = state.last_frame.lock().unwrap();
-    match frame.clone() {
-        Some(data) => Ok(data),
-        None => Err("No frame captured yet".into()),
+fn set_quantization(
+    state: State<Arc<AppState>>,
+    quantize: bool,
+    color_count: u16,
+) -> Result<(), String> {
+    *state.quantize.lock().unwrap() = quantize;
+    *state.color_count.lock().unwrap() = color_count.clamp(2, 256);
+    Ok(())
+}
+
+/// Sets how many frames may be mid-encode at once. Permits already
+/// acquired under the previous limit remain valid until their task
+/// completes; only subsequently spawned encodes observe the new limit.
+#[tauri::command]
+fn set_encode_permits(state: State<Arc<AppState>>, permits: usize) -> Result<(), String> {
+    if permits == 0 {
+        return Err("permits must be at least 1".into());
     }
+
+    *state.encode_permits.lock().unwrap() = Arc::new(Semaphore::new(permits));
+    Ok(())
 }
 
-fn get_rust_target() -> Option<Target> {
-    get_all_targets()
-        .into_iter()
-        .find(|target| matches!(target, Target::Window(window) if window.title == "Rust"))
+/// Enumerates windows and displays available to capture.
+#[tauri::command]
+fn get_targets() -> Vec<TargetSummary> {
+    capture::list_targets()
+}
+
+/// Selects the capture target by id (as returned from `get_targets`) and
+/// optionally its fps/resolution/crop, restarting capture against it.
+/// `resolution` is one of "480p", "720p", "1080p", "1440p", "4k"/"2160p",
+/// or "native" for the target's own size.
+#[tauri::command]
+fn set_target(
+    state: State<Arc<AppState>>,
+    id: u32,
+    fps: Option<u32>,
+    resolution: Option<String>,
+    crop: Option<CropArea>,
+) -> Result<(), String> {
+    let mut config = state.capture_config.lock().unwrap();
+
+    config.target_id = Some(id);
+    if let Some(fps) = fps {
+        config.fps = fps.max(1);
+    }
+    if let Some(resolution) = resolution {
+        config.resolution = capture::parse_resolution(&resolution)?;
+    }
+    config.crop_area = crop;
+    config.version += 1;
+
+    Ok(())
 }
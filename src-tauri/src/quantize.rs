@@ -0,0 +1,164 @@
+//! A from-scratch NeuQuant color quantizer: trains a self-organizing map of
+//! up to 256 "neurons" on sampled pixels, then uses the resulting palette to
+//! remap every pixel to its nearest color via a precomputed inverse map.
+
+/// Number of coarse buckets per color channel in the inverse color map.
+/// 256 levels / 8 per bucket = 32 buckets, giving a 32*32*32 lookup table.
+const BUCKETS_PER_CHANNEL: usize = 32;
+const BUCKET_SHIFT: u32 = 8 - BUCKETS_PER_CHANNEL.trailing_zeros();
+
+#[derive(Clone, Copy)]
+struct Neuron {
+    r: f64,
+    g: f64,
+    b: f64,
+}
+
+impl Neuron {
+    fn to_rgb(self) -> [u8; 3] {
+        [
+            self.r.round().clamp(0.0, 255.0) as u8,
+            self.g.round().clamp(0.0, 255.0) as u8,
+            self.b.round().clamp(0.0, 255.0) as u8,
+        ]
+    }
+
+    fn dist_sq(self, r: f64, g: f64, b: f64) -> f64 {
+        let dr = self.r - r;
+        let dg = self.g - g;
+        let db = self.b - b;
+        dr * dr + dg * dg + db * db
+    }
+}
+
+/// A trained palette plus the inverse map needed to quantize pixels
+/// against it in O(1).
+pub struct NeuQuant {
+    palette: Vec<Neuron>,
+    inverse_map: Vec<u8>,
+}
+
+impl NeuQuant {
+    /// Trains a palette of `color_count` colors (clamped to 2..=256) from
+    /// `rgb_pixels` (tightly packed R,G,B bytes). Only roughly every
+    /// `sample_factor`'th pixel is used during training to keep it fast on
+    /// full frames. Returns `None` if there are fewer pixels than
+    /// `sample_factor`, i.e. too few to sample meaningfully.
+    pub fn train(rgb_pixels: &[u8], color_count: usize, sample_factor: u32) -> Option<Self> {
+        let color_count = color_count.clamp(2, 256);
+        let sample_factor = sample_factor.max(1);
+        let pixel_count = rgb_pixels.len() / 3;
+
+        if pixel_count == 0 || pixel_count < sample_factor as usize {
+            return None;
+        }
+
+        // Initialize neurons spread along the grayscale diagonal.
+        let mut palette: Vec<Neuron> = (0..color_count)
+            .map(|i| {
+                let v = (i * 256 / color_count) as f64;
+                Neuron { r: v, g: v, b: v }
+            })
+            .collect();
+
+        let samples = pixel_count / sample_factor as usize;
+        let mut radius = (color_count / 8).max(1) as f64;
+        let mut alpha = 0.3_f64;
+        let radius_decay = radius / samples.max(1) as f64;
+        let alpha_decay = alpha / samples.max(1) as f64;
+
+        for step in 0..samples {
+            let pixel_index = (step * sample_factor as usize) % pixel_count;
+            let offset = pixel_index * 3;
+            let (r, g, b) = (
+                rgb_pixels[offset] as f64,
+                rgb_pixels[offset + 1] as f64,
+                rgb_pixels[offset + 2] as f64,
+            );
+
+            let winner = Self::nearest_neuron(&palette, r, g, b);
+
+            let radius_i = radius.max(1.0);
+            for (i, neuron) in palette.iter_mut().enumerate() {
+                let d = (i as f64 - winner as f64).abs();
+                if d < radius_i {
+                    let falloff = alpha * (1.0 - d / radius_i);
+                    neuron.r += falloff * (r - neuron.r);
+                    neuron.g += falloff * (g - neuron.g);
+                    neuron.b += falloff * (b - neuron.b);
+                }
+            }
+
+            radius = (radius - radius_decay).max(1.0);
+            alpha = (alpha - alpha_decay).max(0.001);
+        }
+
+        let mut quant = NeuQuant {
+            palette,
+            inverse_map: Vec::new(),
+        };
+        quant.build_inverse_map();
+        Some(quant)
+    }
+
+    fn nearest_neuron(palette: &[Neuron], r: f64, g: f64, b: f64) -> usize {
+        let mut best = 0;
+        let mut best_dist = f64::MAX;
+        for (i, neuron) in palette.iter().enumerate() {
+            let dist = neuron.dist_sq(r, g, b);
+            if dist < best_dist {
+                best_dist = dist;
+                best = i;
+            }
+        }
+        best
+    }
+
+    /// Builds a coarse R/G/B-bucketed index so remapping a pixel to its
+    /// nearest palette entry is an O(1) lookup instead of a linear scan
+    /// over the whole palette.
+    fn build_inverse_map(&mut self) {
+        let buckets = BUCKETS_PER_CHANNEL;
+        let mut map = vec![0u8; buckets * buckets * buckets];
+
+        for r_bucket in 0..buckets {
+            for g_bucket in 0..buckets {
+                for b_bucket in 0..buckets {
+                    let r = (r_bucket << BUCKET_SHIFT) as f64;
+                    let g = (g_bucket << BUCKET_SHIFT) as f64;
+                    let b = (b_bucket << BUCKET_SHIFT) as f64;
+                    let nearest = Self::nearest_neuron(&self.palette, r, g, b);
+                    let index = (r_bucket * buckets + g_bucket) * buckets + b_bucket;
+                    map[index] = nearest as u8;
+                }
+            }
+        }
+
+        self.inverse_map = map;
+    }
+
+    /// Returns the trained palette as RGB triples, in neuron order; the
+    /// index of an entry here is the palette index produced by `map_pixel`.
+    pub fn palette(&self) -> Vec<[u8; 3]> {
+        self.palette.iter().map(|n| n.to_rgb()).collect()
+    }
+
+    /// Maps a single RGB pixel to its nearest palette index via the
+    /// precomputed inverse color map.
+    pub fn map_pixel(&self, r: u8, g: u8, b: u8) -> u8 {
+        let buckets = BUCKETS_PER_CHANNEL;
+        let r_bucket = (r >> BUCKET_SHIFT) as usize;
+        let g_bucket = (g >> BUCKET_SHIFT) as usize;
+        let b_bucket = (b >> BUCKET_SHIFT) as usize;
+        let index = (r_bucket * buckets + g_bucket) * buckets + b_bucket;
+        self.inverse_map[index]
+    }
+
+    /// Remaps a tightly-packed RGB buffer to a buffer of palette indices.
+    pub fn quantize_image(&self, rgb_pixels: &[u8]) -> Vec<u8> {
+        rgb_pixels
+            .chunks_exact(3)
+            .map(|p| self.map_pixel(p[0], p[1], p[2]))
+            .collect()
+    }
+}